@@ -1,34 +1,22 @@
-extern crate chrono;
-extern crate hyper;
-extern crate retry_after;
-extern crate httparse;
-
-use hyper::header::Headers;
-use retry_after::RetryAfter;
+use http::header::HeaderMap;
+use retry_after::{self, RetryAfter};
 
 fn parse_delay() {
-    let raw = [httparse::Header {
-        name: "Retry-After",
-        value: b"300",
-    }];
-
-    let headers = Headers::from_raw(&raw).unwrap();
-    println!("{}", headers);
+    let mut headers = HeaderMap::new();
+    headers.insert(retry_after::HEADER_NAME, "300".parse().unwrap());
 
-    let retry_after = headers.get::<RetryAfter>().unwrap();
+    let retry_after = RetryAfter::from_headers(&headers).unwrap();
     println!("{:?}", retry_after);
 }
 
 fn parse_datetime() {
-    let raw = [httparse::Header {
-        name: "Retry-After",
-        value: b"Sun, 06 Nov 1994 08:49:37 GMT",
-    }];
-
-    let headers = Headers::from_raw(&raw).unwrap();
-    println!("{}", headers);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        retry_after::HEADER_NAME,
+        "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap(),
+    );
 
-    let retry_after = headers.get::<RetryAfter>().unwrap();
+    let retry_after = RetryAfter::from_headers(&headers).unwrap();
     println!("{:?}", retry_after);
 }
 
@@ -0,0 +1,331 @@
+//! Hand-rolled parsing and formatting of the HTTP-date grammars defined in
+//! [RFC7231 §7.1.1.1](http://tools.ietf.org/html/rfc7231#section-7.1.1.1), implemented directly
+//! over `std::time::SystemTime` so the crate doesn't need a full calendar library.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const DAY_NAMES_LONG: [&str; 7] = [
+    "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday",
+];
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A broken-down UTC civil time, as used by all three HTTP-date grammars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Civil {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+impl Civil {
+    fn from_system_time(time: SystemTime) -> Civil {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        let days = secs.div_euclid(86_400);
+        let time_of_day = secs.rem_euclid(86_400);
+
+        let (year, month, day) = days_to_ymd(days);
+        Civil {
+            year,
+            month,
+            day,
+            hour: (time_of_day / 3600) as u32,
+            minute: (time_of_day / 60 % 60) as u32,
+            second: (time_of_day % 60) as u32,
+        }
+    }
+
+    /// Seconds since the epoch, treating the broken-down fields as UTC.
+    fn epoch_secs(self) -> Option<i64> {
+        let days = ymd_to_days(self.year, self.month, self.day);
+        days.checked_mul(86_400)?
+            .checked_add(i64::from(self.hour) * 3600)?
+            .checked_add(i64::from(self.minute) * 60)?
+            .checked_add(i64::from(self.second))
+    }
+
+    fn to_system_time(self) -> Option<SystemTime> {
+        if self.year < 1970 || !self.is_valid() {
+            return None;
+        }
+        let secs = self.epoch_secs()?;
+        if secs < 0 {
+            return None;
+        }
+        Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+    }
+
+    /// Whether each broken-down field falls within its calendar range. This rejects nonsense
+    /// like hour 25 or day 99 that would otherwise be silently fed into the civil-date arithmetic.
+    fn is_valid(self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.hour <= 23
+            && self.minute <= 59
+            && self.second <= 60
+    }
+
+    fn weekday(self) -> usize {
+        let days = ymd_to_days(self.year, self.month, self.day);
+        (days.rem_euclid(7) + 4).rem_euclid(7) as usize
+    }
+}
+
+/// Days-since-epoch to proleptic-Gregorian (year, month, day), after Howard Hinnant's
+/// `civil_from_days` algorithm.
+fn days_to_ymd(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = (z - era * 146_097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+/// Inverse of `days_to_ymd` (Howard Hinnant's `days_from_civil`).
+fn ymd_to_days(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = (y - era * 400) as u64;
+    let doy = (153 * u64::from(if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + u64::from(d) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe as i64 - 719_468
+}
+
+/// Format `time` as IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// Sub-second precision is truncated, since HTTP-dates have one-second resolution.
+pub(crate) fn format_imf_fixdate(time: SystemTime) -> String {
+    let c = Civil::from_system_time(time);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        DAY_NAMES[c.weekday()],
+        c.day,
+        MONTH_NAMES[(c.month - 1) as usize],
+        c.year,
+        c.hour,
+        c.minute,
+        c.second,
+    )
+}
+
+/// Parse an HTTP-date, accepting IMF-fixdate, RFC850, and asctime, in that order.
+///
+/// As a tolerance for non-compliant servers, if none of the three RFC7231 grammars match and the
+/// `rfc3339-fallback` feature is enabled (the default), an RFC3339/ISO-8601 timestamp is also
+/// accepted. Strict callers can disable the `rfc3339-fallback` feature to opt out.
+pub(crate) fn parse_http_date(raw: &str) -> Result<SystemTime, ()> {
+    if let Some(civil) = parse_imf_fixdate(raw)
+        .or_else(|| parse_rfc850(raw))
+        .or_else(|| parse_asctime(raw))
+    {
+        return civil.to_system_time().ok_or(());
+    }
+
+    #[cfg(feature = "rfc3339-fallback")]
+    {
+        if let Some(time) = parse_rfc3339(raw) {
+            return Ok(time);
+        }
+    }
+
+    Err(())
+}
+
+/// Parse an RFC3339/ISO-8601 timestamp, e.g. `1994-11-06T08:49:37Z`.
+///
+/// Accepts both `T` and a single space as the date/time separator, and either a trailing `Z` or
+/// an explicit `±HH:MM` offset; the result is normalized to UTC.
+#[cfg(feature = "rfc3339-fallback")]
+fn parse_rfc3339(s: &str) -> Option<SystemTime> {
+    if !s.is_ascii() {
+        return None;
+    }
+    let b = s.as_bytes();
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    let separator = b[10];
+    if separator != b'T' && separator != b' ' {
+        return None;
+    }
+
+    let year = parse_u32(&s[0..4])?;
+    let month = parse_u32(&s[5..7])?;
+    let day = parse_u32(&s[8..10])?;
+    let hour = parse_u32(&s[11..13])?;
+    let minute = parse_u32(&s[14..16])?;
+    let second = parse_u32(&s[17..19])?;
+
+    let mut rest = &s[19..];
+    if let Some(stripped) = rest.strip_prefix('.') {
+        let digits = stripped.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &stripped[digits..];
+    }
+
+    let offset_secs: i64 = if rest == "Z" || rest == "z" {
+        0
+    } else if rest.len() == 6 && (rest.as_bytes()[0] == b'+' || rest.as_bytes()[0] == b'-') {
+        if rest.as_bytes()[3] != b':' {
+            return None;
+        }
+        let sign = if rest.as_bytes()[0] == b'-' { -1 } else { 1 };
+        let offset_hour = parse_u32(&rest[1..3])?;
+        let offset_minute = parse_u32(&rest[4..6])?;
+        sign * (i64::from(offset_hour) * 3600 + i64::from(offset_minute) * 60)
+    } else {
+        return None;
+    };
+
+    let civil = Civil {
+        year: i64::from(year),
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    };
+    if !civil.is_valid() {
+        return None;
+    }
+    let utc_secs = civil.epoch_secs()?.checked_sub(offset_secs)?;
+    if utc_secs < 0 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::from_secs(utc_secs as u64))
+}
+
+fn month_from_name(name: &str) -> Option<u32> {
+    MONTH_NAMES
+        .iter()
+        .position(|&m| m == name)
+        .map(|i| i as u32 + 1)
+}
+
+fn parse_u32(s: &str) -> Option<u32> {
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// `Sun, 06 Nov 1994 08:49:37 GMT`
+fn parse_imf_fixdate(s: &str) -> Option<Civil> {
+    if !s.is_ascii() {
+        return None;
+    }
+    let b = s.as_bytes();
+    if b.len() != 29 || &s[3..5] != ", " || &s[7..8] != " " || &s[11..12] != " " {
+        return None;
+    }
+    if &s[16..17] != " " || b[19] != b':' || b[22] != b':' || &s[25..29] != " GMT" {
+        return None;
+    }
+
+    let day = parse_u32(&s[5..7])?;
+    let month = month_from_name(&s[8..11])?;
+    let year = parse_u32(&s[12..16])?;
+    let hour = parse_u32(&s[17..19])?;
+    let minute = parse_u32(&s[20..22])?;
+    let second = parse_u32(&s[23..25])?;
+
+    Some(Civil {
+        year: i64::from(year),
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    })
+}
+
+/// `Sunday, 06-Nov-94 08:49:37 GMT`
+fn parse_rfc850(s: &str) -> Option<Civil> {
+    let comma = s.find(", ")?;
+    let weekday = &s[..comma];
+    if !DAY_NAMES_LONG.contains(&weekday) {
+        return None;
+    }
+    let rest = &s[comma + 2..];
+    let rest = rest.strip_suffix(" GMT")?;
+
+    let (date, time) = rest.split_once(' ')?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let day = parse_u32(date_parts.next()?)?;
+    let month = month_from_name(date_parts.next()?)?;
+    let year_2digit = parse_u32(date_parts.next()?)?;
+    if date_parts.next().is_some() {
+        return None;
+    }
+    // Window the 2-digit year around the current century, per the long-standing
+    // cookie/HTTP-date convention: 00-69 -> 20xx, 70-99 -> 19xx.
+    let year = if year_2digit < 70 {
+        2000 + i64::from(year_2digit)
+    } else {
+        1900 + i64::from(year_2digit)
+    };
+
+    let mut time_parts = time.splitn(3, ':');
+    let hour = parse_u32(time_parts.next()?)?;
+    let minute = parse_u32(time_parts.next()?)?;
+    let second = parse_u32(time_parts.next()?)?;
+    if time_parts.next().is_some() {
+        return None;
+    }
+
+    Some(Civil {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    })
+}
+
+/// `Sun Nov  6 08:49:37 1994`
+fn parse_asctime(s: &str) -> Option<Civil> {
+    let b = s.as_bytes();
+    if b.len() != 24 || b[3] != b' ' || b[7] != b' ' || b[10] != b' ' {
+        return None;
+    }
+    if b[13] != b':' || b[16] != b':' || b[19] != b' ' {
+        return None;
+    }
+
+    if !DAY_NAMES.contains(&&s[0..3]) {
+        return None;
+    }
+    let month = month_from_name(&s[4..7])?;
+    let day = parse_u32(s[8..10].trim_start())?;
+    let hour = parse_u32(&s[11..13])?;
+    let minute = parse_u32(&s[14..16])?;
+    let second = parse_u32(&s[17..19])?;
+    let year = parse_u32(&s[20..24])?;
+
+    Some(Civil {
+        year: i64::from(year),
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    })
+}
@@ -23,12 +23,19 @@
 use std::convert::TryFrom;
 use std::time::{Duration, SystemTime};
 
-use http::header::HeaderValue;
-use chrono::{TimeZone, DateTime};
+use http::header::{HeaderMap, HeaderValue};
+
+#[cfg(feature = "chrono")]
 use chrono::offset::Utc;
+#[cfg(feature = "chrono")]
+use chrono::DateTime;
 
 use thiserror::Error;
 
+mod httpdate;
+
+use httpdate::{format_imf_fixdate, parse_http_date};
+
 pub const HEADER_NAME: &str = "Retry-After";
 
 #[derive(Error, Debug)]
@@ -55,6 +62,70 @@ pub enum RetryAfter {
     DateTime(SystemTime),
 }
 
+impl RetryAfter {
+    /// Resolve this header into the concrete instant at which the caller should retry.
+    ///
+    /// For `Delay`, this is `now + delay`; for `DateTime`, it's the stored time unchanged.
+    pub fn instant_from(&self, now: SystemTime) -> SystemTime {
+        match *self {
+            RetryAfter::Delay(delay) => now + delay,
+            RetryAfter::DateTime(datetime) => datetime,
+        }
+    }
+
+    /// Resolve this header into a `Duration` remaining until the caller should retry.
+    ///
+    /// For `Delay`, this is the delay itself; for `DateTime`, it's the time remaining until
+    /// `datetime`, clamped to `Duration::ZERO` if `datetime` is already in the past (servers
+    /// frequently send stale dates).
+    pub fn duration_from(&self, now: SystemTime) -> Duration {
+        match *self {
+            RetryAfter::Delay(delay) => delay,
+            RetryAfter::DateTime(datetime) => datetime.duration_since(now).unwrap_or(Duration::ZERO),
+        }
+    }
+
+    /// Normalize this header against the server's own clock, as given by the response's `Date`
+    /// header, so a relative `Delay` is anchored to the server's time rather than the caller's.
+    ///
+    /// For `DateTime`, the stored time is returned unchanged; for `Delay`, this is
+    /// `response_date + delay`.
+    pub fn to_datetime(&self, response_date: SystemTime) -> SystemTime {
+        match *self {
+            RetryAfter::Delay(delay) => response_date + delay,
+            RetryAfter::DateTime(datetime) => datetime,
+        }
+    }
+
+    /// The inverse of [`to_datetime`](RetryAfter::to_datetime): collapse this header into a
+    /// `Duration` measured from the server's `Date`, clamping to `Duration::ZERO` if the stored
+    /// `DateTime` is already at or before `response_date`.
+    pub fn to_delay(&self, response_date: SystemTime) -> Duration {
+        match *self {
+            RetryAfter::Delay(delay) => delay,
+            RetryAfter::DateTime(datetime) => {
+                datetime.duration_since(response_date).unwrap_or(Duration::ZERO)
+            }
+        }
+    }
+
+    /// Look up and parse the `Retry-After` header from a `HeaderMap`.
+    ///
+    /// Returns `Ok(None)` when the header is absent, rather than an error.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Option<RetryAfter>, FromHeaderValueError> {
+        headers
+            .get(HEADER_NAME)
+            .map(|value| RetryAfter::try_from(value.clone()))
+            .transpose()
+    }
+
+    /// Serialize this header and insert it into `headers` under [`HEADER_NAME`].
+    pub fn apply(self, headers: &mut HeaderMap) {
+        headers.insert(HEADER_NAME, self.into());
+    }
+}
+
+#[cfg(feature = "chrono")]
 impl From<DateTime<Utc>> for RetryAfter {
     fn from(other: DateTime<Utc>) -> RetryAfter {
         RetryAfter::DateTime(From::from(other))
@@ -65,7 +136,7 @@ impl TryFrom<HeaderValue> for RetryAfter {
     type Error = FromHeaderValueError;
 
     fn try_from(header_value: HeaderValue) -> Result<Self, Self::Error> {
-        if header_value.len() == 0 {
+        if header_value.is_empty() {
             return Err(FromHeaderValueError::InsufficientBytes);
         }
 
@@ -76,31 +147,26 @@ impl TryFrom<HeaderValue> for RetryAfter {
             return Ok(RetryAfter::Delay(Duration::from_secs(seconds)));
         }
 
-        // Now, try and parse it as a DateTime.
+        // Now, try and parse it as an HTTP-date.
         parse_http_date(utf8_str)
-            .map(From::from)
+            .map(RetryAfter::DateTime)
             .map_err(|_| FromHeaderValueError::ParseError)
     }
 }
 
-static RFC850_FMT: &'static str =  "%A, %d-%b-%y %T GMT";
-static RFC1123_FMT: &'static str = "%a, %d %b %Y %T GMT";
-static ASCTIME_FMT: &'static str = "%a %b %e %T %Y";
-
-impl Into<HeaderValue> for RetryAfter {
-    fn into(self) -> HeaderValue {
+impl From<RetryAfter> for HeaderValue {
+    fn from(retry_after: RetryAfter) -> HeaderValue {
         use std::io::Write;
         let mut s = Vec::new();
-        match self {
+        match retry_after {
             RetryAfter::Delay(duration) => {
                 write!(&mut s, "{}", duration.as_secs())
                     .expect("write to vec won't fail");
             },
             RetryAfter::DateTime(datetime) => {
-                // According to RFC7231, the sender of an HTTP-date must use the RFC1123 format.
+                // According to RFC7231, the sender of an HTTP-date must use the IMF-fixdate format.
                 // http://tools.ietf.org/html/rfc7231#section-7.1.1.1
-                let datetime: DateTime<Utc> = From::from(datetime);
-                write!(&mut s, "{}", datetime.format(RFC1123_FMT).to_string())
+                write!(&mut s, "{}", format_imf_fixdate(datetime))
                     .expect("write to vec won't fail");
             }
         }
@@ -110,57 +176,32 @@ impl Into<HeaderValue> for RetryAfter {
     }
 }
 
-/// Parse an HTTP-date
-///
-/// HTTP/1.1 servers must return HTTP-dates using RFC1123 format for Retry-After. For compatibility
-/// with HTTP/1.0 servers, RFC850 and ASCTIME formats are supported as well.
-fn parse_http_date(raw: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
-    if let Ok(dt) = Utc.datetime_from_str(raw, RFC1123_FMT) {
-        Ok(dt)
-    } else if let Ok(dt) = Utc.datetime_from_str(raw, RFC850_FMT) {
-        Ok(dt)
-    } else {
-        Utc.datetime_from_str(raw, ASCTIME_FMT)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use std::convert::TryFrom;
+    use std::time::{Duration, UNIX_EPOCH};
 
-    use http::HeaderValue;
-    use chrono::{self, TimeZone};
-    use chrono::offset::Utc;
+    use http::{HeaderMap, HeaderValue};
 
-    use super::{RFC850_FMT, RFC1123_FMT, ASCTIME_FMT};
-    use super::RetryAfter;
+    use super::{RetryAfter, HEADER_NAME};
 
-    macro_rules! test_parse_format {
-        ($name:ident, $fmt:ident, $dt_str:expr) => {
-            #[test]
-            fn $name() {
-                let dt = Utc.ymd(1994, 11, 6).and_hms(8, 49, 37);
+    // 1994-11-06T08:49:37Z, the example instant used throughout RFC7231.
+    const EXAMPLE_DATETIME_SECS: u64 = 784_111_777;
 
-                // Check that the format is what we expect
-                assert_eq!(dt.format($fmt).to_string(), $dt_str);
+    #[test]
+    fn format_imf_fixdate() {
+        let dt = UNIX_EPOCH + Duration::from_secs(EXAMPLE_DATETIME_SECS);
+        let header_value: HeaderValue = RetryAfter::DateTime(dt).into();
 
-                // Check that it parses correctly
-                assert_eq!(Ok(dt), Utc.datetime_from_str($dt_str, $fmt));
-            }
-        }
+        assert_eq!(header_value, "Sun, 06 Nov 1994 08:49:37 GMT");
     }
 
-    test_parse_format!(parse_rfc1123, RFC1123_FMT, "Sun, 06 Nov 1994 08:49:37 GMT");
-    test_parse_format!(parse_rfc850,  RFC850_FMT,  "Sunday, 06-Nov-94 08:49:37 GMT");
-    test_parse_format!(parse_asctime, ASCTIME_FMT, "Sun Nov  6 08:49:37 1994");
-
-
     #[test]
     fn parse_delay() {
         let delay = HeaderValue::from_bytes(b"1234").unwrap();
         let retry_after = RetryAfter::try_from(delay).unwrap();
 
-        assert_eq!(RetryAfter::Delay(std::time::Duration::from_secs(1234)), retry_after);
+        assert_eq!(RetryAfter::Delay(Duration::from_secs(1234)), retry_after);
     }
 
     macro_rules! test_retry_after_datetime {
@@ -169,10 +210,10 @@ mod tests {
             fn $name() {
                 let raw = $bytes.to_vec();
                 let header_value = HeaderValue::from_bytes(&raw[..]).unwrap();
-                let dt = Utc.ymd(1994, 11, 6).and_hms(8, 49, 37);
+                let dt = UNIX_EPOCH + Duration::from_secs(EXAMPLE_DATETIME_SECS);
 
                 let retry_after = RetryAfter::try_from(header_value).expect("parse_header ok");
-                assert_eq!(RetryAfter::DateTime(From::from(dt)), retry_after);
+                assert_eq!(RetryAfter::DateTime(dt), retry_after);
             }
         }
     }
@@ -180,4 +221,180 @@ mod tests {
     test_retry_after_datetime!(header_parse_rfc1123, b"Sun, 06 Nov 1994 08:49:37 GMT");
     test_retry_after_datetime!(header_parse_rfc850, b"Sunday, 06-Nov-94 08:49:37 GMT");
     test_retry_after_datetime!(header_parse_asctime, b"Sun Nov  6 08:49:37 1994");
+
+    #[test]
+    fn rejects_pre_epoch_year() {
+        let header_value = HeaderValue::from_bytes(b"Fri, 06 Nov 1953 08:49:37 GMT").unwrap();
+        assert!(RetryAfter::try_from(header_value).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_fields() {
+        for bad in [
+            &b"Sun, 06 Nov 1994 25:49:37 GMT"[..],
+            &b"Sun, 99 Nov 1994 08:49:37 GMT"[..],
+            &b"Sun, 06 Nov 1994 08:99:37 GMT"[..],
+        ] {
+            let header_value = HeaderValue::from_bytes(bad).unwrap();
+            assert!(RetryAfter::try_from(header_value).is_err());
+        }
+    }
+
+    #[test]
+    fn imf_fixdate_multibyte_char_does_not_panic() {
+        // 29 bytes total, with a 2-byte UTF-8 char straddling the IMF-fixdate's fixed offsets,
+        // so naive byte-index slicing would hit a non-char-boundary and panic.
+        let raw = format!("Su\u{e9}{}", "x".repeat(25));
+        assert_eq!(raw.len(), 29);
+
+        let header_value = HeaderValue::from_bytes(raw.as_bytes()).unwrap();
+        assert!(RetryAfter::try_from(header_value).is_err());
+    }
+
+    #[cfg(feature = "rfc3339-fallback")]
+    macro_rules! test_rfc3339_fallback {
+        ($name:ident, $bytes:expr) => {
+            #[test]
+            fn $name() {
+                let header_value = HeaderValue::from_bytes($bytes).unwrap();
+                let dt = UNIX_EPOCH + Duration::from_secs(EXAMPLE_DATETIME_SECS);
+
+                let retry_after = RetryAfter::try_from(header_value).expect("parse_header ok");
+                assert_eq!(RetryAfter::DateTime(dt), retry_after);
+            }
+        }
+    }
+
+    #[cfg(feature = "rfc3339-fallback")]
+    test_rfc3339_fallback!(header_parse_rfc3339_z, b"1994-11-06T08:49:37Z");
+    #[cfg(feature = "rfc3339-fallback")]
+    test_rfc3339_fallback!(header_parse_rfc3339_space_separator, b"1994-11-06 08:49:37Z");
+    #[cfg(feature = "rfc3339-fallback")]
+    test_rfc3339_fallback!(header_parse_rfc3339_offset, b"1994-11-06T10:49:37+02:00");
+
+    #[cfg(feature = "rfc3339-fallback")]
+    #[test]
+    fn rfc3339_multibyte_char_does_not_panic() {
+        // At least 20 bytes, with a 2-byte UTF-8 char straddling the seconds field's fixed
+        // offsets, so naive byte-index slicing would hit a non-char-boundary and panic.
+        let raw = format!("1994-11-06T08:49:3{}", '\u{e9}');
+        assert!(raw.len() >= 20);
+
+        let header_value = HeaderValue::from_bytes(raw.as_bytes()).unwrap();
+        assert!(RetryAfter::try_from(header_value).is_err());
+    }
+
+    #[cfg(feature = "rfc3339-fallback")]
+    #[test]
+    fn rfc3339_rejects_out_of_range_fields() {
+        let header_value = HeaderValue::from_bytes(b"1994-13-45T08:49:37Z").unwrap();
+        assert!(RetryAfter::try_from(header_value).is_err());
+    }
+
+    #[test]
+    fn instant_from_delay() {
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let retry_after = RetryAfter::Delay(Duration::from_secs(300));
+
+        assert_eq!(retry_after.instant_from(now), now + Duration::from_secs(300));
+    }
+
+    #[test]
+    fn instant_from_datetime() {
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let dt = UNIX_EPOCH + Duration::from_secs(1300);
+        let retry_after = RetryAfter::DateTime(dt);
+
+        assert_eq!(retry_after.instant_from(now), dt);
+    }
+
+    #[test]
+    fn duration_from_delay() {
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let retry_after = RetryAfter::Delay(Duration::from_secs(300));
+
+        assert_eq!(retry_after.duration_from(now), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn duration_from_future_datetime() {
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let dt = UNIX_EPOCH + Duration::from_secs(1300);
+        let retry_after = RetryAfter::DateTime(dt);
+
+        assert_eq!(retry_after.duration_from(now), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn duration_from_past_datetime_clamps_to_zero() {
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let dt = UNIX_EPOCH + Duration::from_secs(500);
+        let retry_after = RetryAfter::DateTime(dt);
+
+        assert_eq!(retry_after.duration_from(now), Duration::ZERO);
+    }
+
+    #[test]
+    fn to_datetime_from_delay() {
+        let response_date = UNIX_EPOCH + Duration::from_secs(1000);
+        let retry_after = RetryAfter::Delay(Duration::from_secs(300));
+
+        assert_eq!(
+            retry_after.to_datetime(response_date),
+            response_date + Duration::from_secs(300)
+        );
+    }
+
+    #[test]
+    fn to_datetime_from_datetime() {
+        let response_date = UNIX_EPOCH + Duration::from_secs(1000);
+        let dt = UNIX_EPOCH + Duration::from_secs(1300);
+        let retry_after = RetryAfter::DateTime(dt);
+
+        assert_eq!(retry_after.to_datetime(response_date), dt);
+    }
+
+    #[test]
+    fn to_delay_from_datetime() {
+        let response_date = UNIX_EPOCH + Duration::from_secs(1000);
+        let dt = UNIX_EPOCH + Duration::from_secs(1300);
+        let retry_after = RetryAfter::DateTime(dt);
+
+        assert_eq!(retry_after.to_delay(response_date), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn to_delay_from_past_datetime_clamps_to_zero() {
+        let response_date = UNIX_EPOCH + Duration::from_secs(1000);
+        let dt = UNIX_EPOCH + Duration::from_secs(500);
+        let retry_after = RetryAfter::DateTime(dt);
+
+        assert_eq!(retry_after.to_delay(response_date), Duration::ZERO);
+    }
+
+    #[test]
+    fn from_headers_absent() {
+        let headers = HeaderMap::new();
+
+        assert_eq!(RetryAfter::from_headers(&headers).unwrap(), None);
+    }
+
+    #[test]
+    fn from_headers_present() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_NAME, HeaderValue::from_static("1234"));
+
+        assert_eq!(
+            RetryAfter::from_headers(&headers).unwrap(),
+            Some(RetryAfter::Delay(Duration::from_secs(1234)))
+        );
+    }
+
+    #[test]
+    fn apply_inserts_header() {
+        let mut headers = HeaderMap::new();
+        RetryAfter::Delay(Duration::from_secs(1234)).apply(&mut headers);
+
+        assert_eq!(headers.get(HEADER_NAME).unwrap(), "1234");
+    }
 }